@@ -2,7 +2,7 @@ use visual_levenshtein::{levenshtein_words, Edit};
 
 fn main() {
     let encoder = |e: Edit| match e {
-        Edit::Equality(s) => format!("{}", s),
+        Edit::Equality(s) => s,
         Edit::Deletion(s) => format!("[-{}-]", s),
         Edit::Insertion(s) => format!("{{+{}+}}", s),
         Edit::Substitution(o, d) => format!("[-{}-]{{+{}+}}", o, d),
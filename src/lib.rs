@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 
 /// Instantiate a Levenshtein calculator.
@@ -56,6 +58,18 @@ pub fn levenshtein_words<'a>(origin: &'a str, dest: &'a str) -> Levenshtein<'a>
     Levenshtein::new_words(origin, dest)
 }
 
+/// Find the grapheme range of `text` with the smallest edit distance to `query`, and
+/// that distance.
+///
+/// Matches may start and end anywhere in `text`; on ties the leftmost end wins.
+/// ```
+/// use visual_levenshtein::best_substring_match;
+/// assert_eq!((4..10, 1), best_substring_match("kiten", "the kitten sat"));
+/// ```
+pub fn best_substring_match(query: &str, text: &str) -> (Range<usize>, usize) {
+    Levenshtein::new(query, text).substring_match()
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Transformation<'a> {
     Init(usize),
@@ -95,6 +109,22 @@ pub enum Edit {
     Substitution(String, String),
 }
 
+/// A position-based operation against the origin string, as consumed by
+/// operational-transform libraries.  Lengths are counted in `char`s.
+#[derive(PartialEq, Debug)]
+pub enum Op {
+    Retain(usize),
+    Delete(usize),
+    Insert(String),
+}
+
+/// Which of two adjacent deletion and insertion groups `grouped_edits()` emits first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeOrder {
+    DeleteFirst,
+    InsertFirst,
+}
+
 #[derive(Debug)]
 pub struct Levenshtein<'a> {
     x_dim: usize,
@@ -102,6 +132,9 @@ pub struct Levenshtein<'a> {
     origin: Vec<&'a str>,
     dest: Vec<&'a str>,
     matrix: Vec<Vec<Transformation<'a>>>,
+    // origin token -> dest token -> cost, so lookups need no allocation
+    substitution_costs: HashMap<String, HashMap<String, usize>>,
+    change_order: ChangeOrder,
 }
 
 impl<'a> Levenshtein<'a> {
@@ -110,7 +143,8 @@ impl<'a> Levenshtein<'a> {
         let dest = UnicodeSegmentation::graphemes(d, true).collect::<Vec<&'a str>>();
         let x_dim = origin.len() + 1;
         let y_dim = dest.len() + 1;
-        let matrix = vec![vec![Transformation::Init(0); y_dim]; x_dim];
+        // allocated by `initialize`, so that cheap queries don't pay for it
+        let matrix = vec![];
 
         Self {
             x_dim,
@@ -118,6 +152,8 @@ impl<'a> Levenshtein<'a> {
             origin,
             dest,
             matrix,
+            substitution_costs: HashMap::new(),
+            change_order: ChangeOrder::DeleteFirst,
         }
     }
 
@@ -126,7 +162,8 @@ impl<'a> Levenshtein<'a> {
         let dest = UnicodeSegmentation::split_word_bounds(d).collect::<Vec<&'a str>>();
         let x_dim = origin.len() + 1;
         let y_dim = dest.len() + 1;
-        let matrix = vec![vec![Transformation::Init(0); y_dim]; x_dim];
+        // allocated by `initialize`, so that cheap queries don't pay for it
+        let matrix = vec![];
 
         Self {
             x_dim,
@@ -134,9 +171,96 @@ impl<'a> Levenshtein<'a> {
             origin,
             dest,
             matrix,
+            substitution_costs: HashMap::new(),
+            change_order: ChangeOrder::DeleteFirst,
         }
     }
 
+    /// Override the cost of substituting specific tokens.
+    ///
+    /// Keys are `(origin token, dest token)` pairs, so lookups are direction-aware;
+    /// pairs not in the table cost 1 as usual.
+    /// ```
+    /// use std::collections::HashMap;
+    /// use visual_levenshtein::levenshtein;
+    /// let mut costs = HashMap::new();
+    /// costs.insert(("0".to_string(), "O".to_string()), 0);
+    /// assert_eq!(0, levenshtein("0CR", "OCR").with_substitution_costs(costs).distance());
+    /// ```
+    pub fn with_substitution_costs(mut self, costs: HashMap<(String, String), usize>) -> Self {
+        let mut table: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for ((o, d), cost) in costs {
+            table.entry(o).or_default().insert(d, cost);
+        }
+        self.substitution_costs = table;
+        self
+    }
+
+    /// Choose whether a deletion group or an insertion group comes first when the two
+    /// are adjacent in the grouped edits.  The default, `ChangeOrder::DeleteFirst`,
+    /// matches the order the traceback naturally produces.  The distance is unaffected.
+    ///
+    /// With the default substitution cost of 1 a deletion is never adjacent to an
+    /// insertion (the pair costs 2, a substitution only 1), so this option only has an
+    /// effect when `with_substitution_costs` makes some substitutions cost 2 or more.
+    /// ```
+    /// use std::collections::HashMap;
+    /// use visual_levenshtein::{levenshtein, ChangeOrder, Edit};
+    /// let mut costs = HashMap::new();
+    /// costs.insert(("a".to_string(), "b".to_string()), 5);
+    /// let test = levenshtein("a", "b")
+    ///     .with_substitution_costs(costs)
+    ///     .change_order(ChangeOrder::InsertFirst)
+    ///     .grouped_edits();
+    /// let expected = vec![
+    ///     Edit::Insertion("b".to_string()),
+    ///     Edit::Deletion("a".to_string()),
+    /// ];
+    /// assert_eq!(expected, test);
+    /// ```
+    pub fn change_order(mut self, order: ChangeOrder) -> Self {
+        self.change_order = order;
+        self
+    }
+
+    /// Drop every token of `origin` and `dest` for which `ignore` returns true before
+    /// any calculation, so ignored tokens neither count toward the distance nor
+    /// appear in the edits.
+    /// ```
+    /// use visual_levenshtein::{levenshtein_words, Edit};
+    /// let test = levenshtein_words("one  two", "one\ttwo three")
+    ///     .ignore_tokens(|t| t.trim().is_empty())
+    ///     .grouped_edits();
+    /// let expected = vec![
+    ///     Edit::Equality("onetwo".to_string()),
+    ///     Edit::Insertion("three".to_string()),
+    /// ];
+    /// assert_eq!(expected, test);
+    /// ```
+    pub fn ignore_tokens<F>(mut self, ignore: F) -> Self
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.origin.retain(|t| !ignore(t));
+        self.dest.retain(|t| !ignore(t));
+        self.x_dim = self.origin.len() + 1;
+        self.y_dim = self.dest.len() + 1;
+        // reallocated at the new size by `initialize`
+        self.matrix.clear();
+        self
+    }
+
+    /// Number of tokens (graphemes or words, depending on constructor) in the origin.
+    /// Neither this nor `dest_len` allocates or computes the matrix.
+    pub fn origin_len(&self) -> usize {
+        self.origin.len()
+    }
+
+    /// Number of tokens (graphemes or words, depending on constructor) in the dest.
+    pub fn dest_len(&self) -> usize {
+        self.dest.len()
+    }
+
     fn value_at(&self, x: usize, y: usize) -> Transformation<'a> {
         self.matrix[x][y].clone()
     }
@@ -146,6 +270,9 @@ impl<'a> Levenshtein<'a> {
     }
 
     fn initialize(&mut self) {
+        if self.matrix.is_empty() {
+            self.matrix = vec![vec![Transformation::Init(0); self.y_dim]; self.x_dim];
+        }
         self.set_value(0, 0, Transformation::Init(0));
         for x in 1..self.x_dim {
             self.set_value(x, 0, Transformation::Deletion(x, self.origin[x - 1]));
@@ -166,6 +293,7 @@ impl<'a> Levenshtein<'a> {
                     self.value_at(x - 1, y - 1).cost(),
                     self.origin[x - 1],
                     self.dest[y - 1],
+                    &self.substitution_costs,
                 );
                 self.set_value(x, y, t_min_3(&deletion, &insertion, &sub_or_eq).clone());
             }
@@ -180,15 +308,102 @@ impl<'a> Levenshtein<'a> {
         self.value_at(x, y).cost()
     }
 
+    /// A cheap lower bound on `distance()`: the difference in token counts between
+    /// `origin` and `dest`.
+    ///
+    /// The matrix is neither allocated nor computed, so this is useful for rejecting
+    /// obvious non-matches before paying for the full O(m*n) calculation.  The bound
+    /// is loose (e.g. it is 0 for any two strings of equal length) but never exceeds
+    /// the true distance.
+    /// ```
+    /// use visual_levenshtein::levenshtein;
+    /// let mut c = levenshtein("kitten", "sitting");
+    /// assert_eq!(1, c.distance_lower_bound());
+    /// assert_eq!(3, c.distance());
+    /// ```
+    pub fn distance_lower_bound(&self) -> usize {
+        self.origin_len().abs_diff(self.dest_len())
+    }
+
+    /// Minimum cost found in the last column of the matrix, i.e. the cells
+    /// `(x, dest.len())` for every `x` in `0..=origin.len()`.
+    ///
+    /// Each such cell holds the distance between a prefix of `origin` and the whole
+    /// of `dest`, so the minimum is a lower bound on the distance between `origin`
+    /// and any string that begins with `dest`.  This is the usual test for pruning
+    /// a trie walk: if it exceeds your threshold, no extension of `dest` can match.
+    /// ```
+    /// use visual_levenshtein::levenshtein;
+    /// assert_eq!(0, levenshtein("kitten", "kit").prefix_distance());
+    /// assert_eq!(1, levenshtein("kitten", "sit").prefix_distance());
+    /// ```
+    pub fn prefix_distance(&mut self) -> usize {
+        let y = self.dest.len();
+        self.initialize();
+        self.calculate_matrix();
+        (0..self.x_dim)
+            .map(|x| self.matrix[x][y].cost())
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn substring_match(&mut self) -> (Range<usize>, usize) {
+        let x = self.origin.len();
+        self.initialize();
+        // A match may begin anywhere in dest, so skipping its leading tokens is free.
+        for y in 1..self.y_dim {
+            self.set_value(0, y, Transformation::Init(0));
+        }
+        self.calculate_matrix();
+
+        let mut end = 0;
+        for y in 1..self.y_dim {
+            if self.value_at(x, y).cost() < self.value_at(x, end).cost() {
+                end = y;
+            }
+        }
+        let cost = self.value_at(x, end).cost();
+
+        let (_, (_, start)) = self.trace_back(x, end, |x, _| x > 0);
+
+        (start..end, cost)
+    }
+
     pub fn raw_edits(&mut self) -> Vec<Transformation<'a>> {
-        let mut x = self.origin.len();
-        let mut y = self.dest.len();
+        self.traceback()
+            .into_iter()
+            .map(|(x, y)| self.value_at(x, y))
+            .collect()
+    }
+
+    /// Calculate the matrix and return the cells on the edit path, in origin -> dest
+    /// order, excluding the initial cell.
+    fn traceback(&mut self) -> Vec<(usize, usize)> {
+        let x = self.origin.len();
+        let y = self.dest.len();
         self.initialize();
         self.calculate_matrix();
-        let mut transformations: Vec<Transformation<'a>> = vec![];
-        while x > 0 || y > 0 {
-            let next = self.value_at(x, y);
-            match next {
+        let (path, _) = self.trace_back(x, y, |x, y| x > 0 || y > 0);
+
+        path
+    }
+
+    /// Follow the computed matrix back from cell `(x, y)` for as long as `more(x, y)`
+    /// holds.  Returns the cells visited, in origin -> dest order, and the cell where
+    /// the walk stopped.
+    fn trace_back<F>(
+        &self,
+        mut x: usize,
+        mut y: usize,
+        more: F,
+    ) -> (Vec<(usize, usize)>, (usize, usize))
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let mut path: Vec<(usize, usize)> = vec![];
+        while more(x, y) {
+            path.push((x, y));
+            match self.matrix[x][y] {
                 Transformation::Insertion(_, _) => {
                     y -= 1;
                 }
@@ -200,25 +415,56 @@ impl<'a> Levenshtein<'a> {
                     y -= 1;
                 }
                 Transformation::Init(_) => {
-                    unimplemented!("This should only be reached if x == 0 && y == 0!")
+                    unimplemented!("Init cells should only be reached at the end of a walk!")
                 }
             }
-            transformations.push(next);
         }
 
-        transformations.reverse();
+        path.reverse();
 
-        transformations
+        (path, (x, y))
     }
 
     pub fn grouped_edits(&mut self) -> Vec<Edit> {
-        let raw = self.raw_edits();
         let mut grouped: Vec<Edit> = vec![];
+        self.for_each_grouped_edit(|e| grouped.push(e));
+
+        grouped
+    }
+
+    /// Call `f` with each grouped edit, in the same order as `grouped_edits()`,
+    /// without collecting them into a `Vec<Edit>` first.
+    ///
+    /// This does not stream the calculation itself: the full matrix is computed and
+    /// the edit path is traced back (as a list of matrix cells) before the first
+    /// call.  Only the grouped edits' strings are built one at a time, as each group
+    /// is completed.  A deletion or insertion group is held until the following
+    /// group is known, so that adjacent changes can be emitted in the configured
+    /// `ChangeOrder`.
+    /// ```
+    /// use visual_levenshtein::levenshtein;
+    /// let mut groups = 0;
+    /// levenshtein("kitten", "sitting").for_each_grouped_edit(|_| groups += 1);
+    /// assert_eq!(5, groups);
+    /// ```
+    pub fn for_each_grouped_edit<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Edit),
+    {
+        let path = self.traceback();
+        if path.is_empty() {
+            return;
+        }
+        let raw = |i: usize| {
+            let (x, y) = path[i];
+            &self.matrix[x][y]
+        };
         let mut bin: Vec<&'a str> = vec![];
         let mut sub_dest_bin: Vec<&'a str> = vec![];
+        let mut pending: Option<Edit> = None;
         let mut i: usize = 0;
-        let mut current_t = raw[i].t();
-        match raw[i] {
+        let mut current_t = raw(i).t();
+        match *raw(i) {
             Transformation::Equality(_, e) => bin.push(e),
             Transformation::Deletion(_, e) => bin.push(e),
             Transformation::Insertion(_, e) => bin.push(e),
@@ -230,10 +476,10 @@ impl<'a> Levenshtein<'a> {
         }
         i += 1;
 
-        while i < raw.len() {
-            while i < raw.len() && raw[i].t() == current_t {
+        while current_t != 0 {
+            while i < path.len() && raw(i).t() == current_t {
                 // push to bins
-                match raw[i] {
+                match *raw(i) {
                     Transformation::Equality(_, e) => bin.push(e),
                     Transformation::Deletion(_, e) => bin.push(e),
                     Transformation::Insertion(_, e) => bin.push(e),
@@ -247,31 +493,82 @@ impl<'a> Levenshtein<'a> {
                 }
                 i += 1;
             }
-            // concatenate bins and push transform/s to grouped
-            match current_t {
-                1 => {
-                    grouped.push(Edit::Equality(bin.join("")));
-                }
-                2 => {
-                    grouped.push(Edit::Deletion(bin.join("")));
-                }
-                3 => {
-                    grouped.push(Edit::Insertion(bin.join("")));
-                }
-                4 => {
-                    grouped.push(Edit::Substitution(bin.join(""), sub_dest_bin.join("")));
-                }
+            // concatenate bins into a transform
+            let edit = match current_t {
+                1 => Edit::Equality(bin.join("")),
+                2 => Edit::Deletion(bin.join("")),
+                3 => Edit::Insertion(bin.join("")),
+                4 => Edit::Substitution(bin.join(""), sub_dest_bin.join("")),
                 _ => {
                     unimplemented!("This should never appear in raw edits!");
                 }
+            };
+            // emit it, holding a deletion or insertion back until we know whether the
+            // next group is its counterpart and must be ordered against it
+            match edit {
+                Edit::Deletion(_) | Edit::Insertion(_) => match pending.take() {
+                    Some(p) => {
+                        let p_first = matches!(
+                            (self.change_order, &p),
+                            (ChangeOrder::DeleteFirst, Edit::Deletion(_))
+                                | (ChangeOrder::InsertFirst, Edit::Insertion(_))
+                        );
+                        if p_first {
+                            f(p);
+                            f(edit);
+                        } else {
+                            f(edit);
+                            f(p);
+                        }
+                    }
+                    None => pending = Some(edit),
+                },
+                _ => {
+                    if let Some(p) = pending.take() {
+                        f(p);
+                    }
+                    f(edit);
+                }
             }
-            current_t = if i < raw.len() { raw[i].t() } else { 0 };
+            current_t = if i < path.len() { raw(i).t() } else { 0 };
             // clear bins
             bin = vec![];
             sub_dest_bin = vec![];
         }
+        if let Some(p) = pending {
+            f(p);
+        }
+    }
+
+    /// Express the grouped edits as a sequence of `Op`s that, applied in order to the
+    /// origin, produce the destination.  Substitutions become a delete followed by an
+    /// insert.
+    /// ```
+    /// use visual_levenshtein::{levenshtein, Op};
+    /// let test = levenshtein("cat", "cups").to_ops();
+    /// let expected = vec![
+    ///     Op::Retain(1),
+    ///     Op::Insert("u".to_string()),
+    ///     Op::Delete(2),
+    ///     Op::Insert("ps".to_string()),
+    /// ];
+    /// assert_eq!(expected, test);
+    /// ```
+    pub fn to_ops(&mut self) -> Vec<Op> {
+        let mut ops: Vec<Op> = vec![];
+        for edit in self.grouped_edits() {
+            match edit {
+                Edit::Equality(s) => ops.push(Op::Retain(s.chars().count())),
+                Edit::Deletion(s) => ops.push(Op::Delete(s.chars().count())),
+                Edit::Insertion(s) => ops.push(Op::Insert(s)),
+                Edit::Substitution(o, d) => {
+                    ops.push(Op::Delete(o.chars().count()));
+                    ops.push(Op::Insert(d));
+                }
+            }
+        }
 
-        grouped
+        ops
     }
 
     pub fn encoded_edits<F>(&mut self, encoder: F) -> String
@@ -279,12 +576,78 @@ impl<'a> Levenshtein<'a> {
         F: Fn(Edit) -> String,
     {
         let grouped = self.grouped_edits();
-        let components: Vec<String> = grouped.into_iter().map(|g| encoder(g)).collect();
+        let components: Vec<String> = grouped.into_iter().map(encoder).collect();
+
+        components.join("")
+    }
+
+    /// Like `encoded_edits`, but each substitution is rendered as a grapheme-level
+    /// diff of the replaced and replacing text, encoded with `char_encoder`.  All other
+    /// edits are encoded with `word_encoder`.  This is mostly useful in word mode, to
+    /// highlight just the changed characters of a substituted word.
+    /// ```
+    /// use visual_levenshtein::{levenshtein_words, Edit};
+    /// let encoder = |e: Edit| match e {
+    ///     Edit::Equality(s) => s,
+    ///     Edit::Deletion(s) => format!("[-{}-]", s),
+    ///     Edit::Insertion(s) => format!("{{+{}+}}", s),
+    ///     Edit::Substitution(o, d) => format!("[-{}-]{{+{}+}}", o, d),
+    /// };
+    /// let test = levenshtein_words("day in spring", "day in Spring")
+    ///     .encoded_edits_nested(encoder, encoder);
+    /// assert_eq!("day in [-s-]{+S+}pring".to_string(), test);
+    /// ```
+    pub fn encoded_edits_nested<F, G>(&mut self, word_encoder: F, char_encoder: G) -> String
+    where
+        F: Fn(Edit) -> String,
+        G: Fn(Edit) -> String,
+    {
+        let grouped = self.grouped_edits();
+        let components: Vec<String> = grouped
+            .into_iter()
+            .map(|g| match g {
+                Edit::Substitution(o, d) => levenshtein(&o, &d).encoded_edits(&char_encoder),
+                _ => word_encoder(g),
+            })
+            .collect();
 
         components.join("")
     }
 }
 
+/// A ready-made encoder that renders substitutions in place as `{~new~}`.
+///
+/// Deletions and insertions use `wdiff`-like `[-old-]` and `{+new+}` notation.  Use
+/// `replace_encoder_with` to also render the replaced text.
+/// ```
+/// use visual_levenshtein::{levenshtein, replace_encoder};
+/// let test = levenshtein("cat", "cup").encoded_edits(replace_encoder);
+/// assert_eq!("c{~up~}".to_string(), test);
+/// ```
+pub fn replace_encoder(edit: Edit) -> String {
+    replace_encoder_with(|_, d| format!("{{~{}~}}", d))(edit)
+}
+
+/// Like `replace_encoder`, but substitutions are rendered by `substitution`, which is
+/// given the replaced and the replacing text, in that order.
+/// ```
+/// use visual_levenshtein::{levenshtein, replace_encoder_with};
+/// let encoder = replace_encoder_with(|o, d| format!("<span title=\"{}\">{}</span>", o, d));
+/// let test = levenshtein("cat", "cup").encoded_edits(encoder);
+/// assert_eq!("c<span title=\"at\">up</span>".to_string(), test);
+/// ```
+pub fn replace_encoder_with<F>(substitution: F) -> impl Fn(Edit) -> String
+where
+    F: Fn(&str, &str) -> String,
+{
+    move |edit: Edit| match edit {
+        Edit::Equality(s) => s,
+        Edit::Deletion(s) => format!("[-{}-]", s),
+        Edit::Insertion(s) => format!("{{+{}+}}", s),
+        Edit::Substitution(o, d) => substitution(&o, &d),
+    }
+}
+
 fn t_min_3<'a, 'b>(
     insertion: &'b Transformation<'a>,
     deletion: &'b Transformation<'a>,
@@ -302,11 +665,21 @@ fn t_min_3<'a, 'b>(
     }
 }
 
-fn t_delta<'a>(from_cost: usize, origin: &'a str, dest: &'a str) -> Transformation<'a> {
+fn t_delta<'a>(
+    from_cost: usize,
+    origin: &'a str,
+    dest: &'a str,
+    costs: &HashMap<String, HashMap<String, usize>>,
+) -> Transformation<'a> {
     if origin == dest {
         Transformation::Equality(from_cost, dest)
     } else {
-        Transformation::Substitution(from_cost + 1, origin, dest)
+        let cost = costs
+            .get(origin)
+            .and_then(|m| m.get(dest))
+            .copied()
+            .unwrap_or(1);
+        Transformation::Substitution(from_cost + cost, origin, dest)
     }
 }
 
@@ -380,6 +753,151 @@ mod tests {
         assert_eq!(2, levenshtein("abcdefg", "gabcdef").distance());
     }
 
+    #[test]
+    fn len_checks() {
+        let c = levenshtein("kitten", "sitting");
+        assert_eq!(6, c.origin_len());
+        assert_eq!(7, c.dest_len());
+        assert!(c.matrix.is_empty());
+
+        let c = levenshtein("a̐🇸🇹", "");
+        assert_eq!(2, c.origin_len());
+        assert_eq!(0, c.dest_len());
+
+        let c = levenshtein_words("one too many", "one too much, hey");
+        assert_eq!(5, c.origin_len());
+        assert_eq!(8, c.dest_len());
+    }
+
+    #[test]
+    fn distance_lower_bound_checks() {
+        let examples = vec![
+            ("same", "same"),
+            ("", ""),
+            ("kitten", "sitting"),
+            ("", "abc"),
+            ("abc", ""),
+            ("1234567", "7654321"),
+            ("Saturday", "Sunday"),
+        ];
+        for (o, d) in examples {
+            let mut c = levenshtein(o, d);
+            assert!(c.distance_lower_bound() <= c.distance());
+        }
+        assert_eq!(3, levenshtein("", "abc").distance_lower_bound());
+        assert_eq!(2, levenshtein("Saturday", "Sunday").distance_lower_bound());
+        assert_eq!(0, levenshtein("1234567", "7654321").distance_lower_bound());
+
+        let c = levenshtein("kitten", "sit");
+        assert_eq!(3, c.distance_lower_bound());
+        assert!(c.matrix.is_empty());
+    }
+
+    #[test]
+    fn ignore_tokens_checks() {
+        let is_space = |t: &str| t.trim().is_empty();
+        assert_eq!(2, levenshtein_words("one two", "one  two  ").distance());
+        assert_eq!(
+            0,
+            levenshtein_words("one two", "one  two  ")
+                .ignore_tokens(is_space)
+                .distance()
+        );
+
+        let mut c = levenshtein_words("one two", "one  two  ");
+        assert_eq!(2, c.distance());
+        assert_eq!(0, c.ignore_tokens(is_space).distance());
+
+        let test = levenshtein_words("one fine day", "one  fine\tnight")
+            .ignore_tokens(is_space)
+            .grouped_edits();
+        let expected = vec![
+            Edit::Equality("onefine".to_string()),
+            Edit::Substitution("day".to_string(), "night".to_string()),
+        ];
+        assert_eq!(expected, test);
+
+        let mut c = levenshtein_words("   ", "one two").ignore_tokens(is_space);
+        assert_eq!(0, c.origin_len());
+        assert_eq!(2, c.dest_len());
+        assert!(c.matrix.is_empty());
+        assert_eq!(
+            vec![Edit::Insertion("onetwo".to_string())],
+            c.grouped_edits()
+        );
+
+        let test = levenshtein_words("one two", " ")
+            .ignore_tokens(is_space)
+            .grouped_edits();
+        assert_eq!(vec![Edit::Deletion("onetwo".to_string())], test);
+
+        let test = levenshtein_words(" ", "\t")
+            .ignore_tokens(is_space)
+            .grouped_edits();
+        assert_eq!(Vec::<Edit>::new(), test);
+    }
+
+    #[test]
+    fn prefix_distance_checks() {
+        assert_eq!(0, levenshtein("kitten", "kit").prefix_distance());
+        assert_eq!(1, levenshtein("kitten", "sit").prefix_distance());
+        assert_eq!(2, levenshtein("kitten", "kitchen").prefix_distance());
+        assert_eq!(0, levenshtein("abc", "").prefix_distance());
+        assert_eq!(3, levenshtein("", "abc").prefix_distance());
+
+        let mut c = levenshtein("kitten", "kitchen");
+        assert_eq!(2, c.prefix_distance());
+        assert_eq!(2, c.distance());
+    }
+
+    #[test]
+    fn best_substring_match_checks() {
+        assert_eq!((4..10, 1), best_substring_match("kiten", "the kitten sat"));
+        assert_eq!((4..10, 0), best_substring_match("kitten", "the kitten sat"));
+        assert_eq!((0..3, 0), best_substring_match("the", "the kitten sat"));
+        assert_eq!((11..14, 1), best_substring_match("set", "the kitten sat"));
+        assert_eq!((0..0, 0), best_substring_match("", "the kitten sat"));
+        assert_eq!((0..0, 3), best_substring_match("abc", ""));
+        assert_eq!((1..3, 0), best_substring_match("🇸🇹a̐", "x🇸🇹a̐y"));
+    }
+
+    #[test]
+    fn substitution_cost_checks() {
+        let mut costs = HashMap::new();
+        costs.insert(("0".to_string(), "O".to_string()), 0);
+        costs.insert(("l".to_string(), "1".to_string()), 0);
+        costs.insert(("rn".to_string(), "m".to_string()), 0);
+        assert_eq!(2, levenshtein("C0L1", "COLl").distance());
+        assert_eq!(
+            1,
+            levenshtein("C0L1", "COLl")
+                .with_substitution_costs(costs.clone())
+                .distance()
+        );
+        // Lookups are direction-aware.
+        assert_eq!(
+            1,
+            levenshtein("O", "0")
+                .with_substitution_costs(costs.clone())
+                .distance()
+        );
+        assert_eq!(
+            0,
+            levenshtein_words("rn", "m")
+                .with_substitution_costs(costs)
+                .distance()
+        );
+
+        let mut costs = HashMap::new();
+        costs.insert(("a".to_string(), "b".to_string()), 5);
+        assert_eq!(
+            2,
+            levenshtein("a", "b")
+                .with_substitution_costs(costs)
+                .distance()
+        );
+    }
+
     #[test]
     fn raw_edit_checks() {
         // See examples at: https://en.wikipedia.org/wiki/Levenshtein_distance#Iterative_with_full_matrix
@@ -438,12 +956,158 @@ mod tests {
         test = levenshtein("abc", "def").grouped_edits();
         expected = vec![Edit::Substitution("abc".to_string(), "def".to_string())];
         assert_eq!(expected, test);
+
+        test = levenshtein("a", "b").grouped_edits();
+        expected = vec![Edit::Substitution("a".to_string(), "b".to_string())];
+        assert_eq!(expected, test);
+
+        test = levenshtein("a", "").grouped_edits();
+        expected = vec![Edit::Deletion("a".to_string())];
+        assert_eq!(expected, test);
+
+        test = levenshtein("", "a").grouped_edits();
+        expected = vec![Edit::Insertion("a".to_string())];
+        assert_eq!(expected, test);
+
+        test = levenshtein("", "").grouped_edits();
+        expected = vec![];
+        assert_eq!(expected, test);
+    }
+
+    #[test]
+    fn grouped_edit_checks_empty_words() {
+        let mut test = levenshtein_words("", "one two").grouped_edits();
+        let mut expected = vec![Edit::Insertion("one two".to_string())];
+        assert_eq!(expected, test);
+
+        test = levenshtein_words("one two", "").grouped_edits();
+        expected = vec![Edit::Deletion("one two".to_string())];
+        assert_eq!(expected, test);
+
+        test = levenshtein_words("", "").grouped_edits();
+        expected = vec![];
+        assert_eq!(expected, test);
+    }
+
+    fn apply_ops(origin: &str, ops: Vec<Op>) -> String {
+        let mut chars = origin.chars();
+        let mut result = String::new();
+        for op in ops {
+            match op {
+                Op::Retain(n) => result.extend(chars.by_ref().take(n)),
+                Op::Delete(n) => {
+                    chars.by_ref().take(n).for_each(drop);
+                }
+                Op::Insert(s) => result.push_str(&s),
+            }
+        }
+        assert_eq!(None, chars.next());
+
+        result
+    }
+
+    #[test]
+    fn to_ops_checks() {
+        let test = levenshtein("Saturday", "Sunday").to_ops();
+        let expected = vec![
+            Op::Retain(1),
+            Op::Delete(2),
+            Op::Retain(1),
+            Op::Delete(1),
+            Op::Insert("n".to_string()),
+            Op::Retain(3),
+        ];
+        assert_eq!(expected, test);
+        assert_eq!("Sunday", apply_ops("Saturday", test));
+
+        let examples = vec![
+            ("kitten", "sitting"),
+            ("One fine day in spring", "One fine man said May day"),
+            ("", "abc"),
+            ("abc", ""),
+            ("a̐éö̲", "a̐ö̲🇸🇹"),
+        ];
+        for (o, d) in examples {
+            assert_eq!(d, apply_ops(o, levenshtein(o, d).to_ops()));
+            assert_eq!(d, apply_ops(o, levenshtein_words(o, d).to_ops()));
+        }
+    }
+
+    #[test]
+    fn change_order_checks() {
+        let mut costs = HashMap::new();
+        costs.insert(("a".to_string(), "b".to_string()), 5);
+
+        let mut c = levenshtein("xaay", "xbby").with_substitution_costs(costs.clone());
+        let expected = vec![
+            Edit::Equality("x".to_string()),
+            Edit::Deletion("aa".to_string()),
+            Edit::Insertion("bb".to_string()),
+            Edit::Equality("y".to_string()),
+        ];
+        assert_eq!(expected, c.grouped_edits());
+        assert_eq!(4, c.distance());
+
+        let mut c = levenshtein("xaay", "xbby")
+            .with_substitution_costs(costs.clone())
+            .change_order(ChangeOrder::DeleteFirst);
+        assert_eq!(expected, c.grouped_edits());
+
+        let mut c = levenshtein("xaay", "xbby")
+            .with_substitution_costs(costs.clone())
+            .change_order(ChangeOrder::InsertFirst);
+        let expected = vec![
+            Edit::Equality("x".to_string()),
+            Edit::Insertion("bb".to_string()),
+            Edit::Deletion("aa".to_string()),
+            Edit::Equality("y".to_string()),
+        ];
+        assert_eq!(expected, c.grouped_edits());
+        assert_eq!(4, c.distance());
+
+        // Lone deletions and insertions are unaffected.
+        let test = levenshtein("Saturday", "Sunday")
+            .change_order(ChangeOrder::InsertFirst)
+            .grouped_edits();
+        assert_eq!(levenshtein("Saturday", "Sunday").grouped_edits(), test);
+        let test = levenshtein("kitten", "sitting")
+            .change_order(ChangeOrder::InsertFirst)
+            .grouped_edits();
+        assert_eq!(levenshtein("kitten", "sitting").grouped_edits(), test);
+
+        // With default costs a replaced region is a single substitution.
+        let test = levenshtein("abc", "xyz")
+            .change_order(ChangeOrder::InsertFirst)
+            .grouped_edits();
+        let expected = vec![Edit::Substitution("abc".to_string(), "xyz".to_string())];
+        assert_eq!(expected, test);
+    }
+
+    #[test]
+    fn for_each_grouped_edit_matches_grouped_edits() {
+        let examples = vec![
+            ("kitten", "sitting"),
+            ("Saturday", "Sunday"),
+            ("", ""),
+            ("", "abc"),
+            ("abc", ""),
+            ("One fine day in spring", "One fine man said May day"),
+        ];
+        for (o, d) in examples {
+            let mut streamed: Vec<Edit> = vec![];
+            levenshtein(o, d).for_each_grouped_edit(|e| streamed.push(e));
+            assert_eq!(levenshtein(o, d).grouped_edits(), streamed);
+
+            let mut streamed: Vec<Edit> = vec![];
+            levenshtein_words(o, d).for_each_grouped_edit(|e| streamed.push(e));
+            assert_eq!(levenshtein_words(o, d).grouped_edits(), streamed);
+        }
     }
 
     #[test]
     fn encoded_edits_checks() {
         let encoder = |e: Edit| match e {
-            Edit::Equality(s) => format!("{}", s),
+            Edit::Equality(s) => s,
             Edit::Deletion(s) => format!("[-{}-]", s),
             Edit::Insertion(s) => format!("{{+{}+}}", s),
             Edit::Substitution(o, d) => format!("[-{}-]{{+{}+}}", o, d),
@@ -453,6 +1117,52 @@ mod tests {
         assert_eq!(expected, test);
     }
 
+    #[test]
+    fn encoded_edits_nested_checks() {
+        let encoder = |e: Edit| match e {
+            Edit::Equality(s) => s,
+            Edit::Deletion(s) => format!("[-{}-]", s),
+            Edit::Insertion(s) => format!("{{+{}+}}", s),
+            Edit::Substitution(o, d) => format!("[-{}-]{{+{}+}}", o, d),
+        };
+        let test = levenshtein_words("spring", "Spring").encoded_edits_nested(encoder, encoder);
+        let expected = "[-s-]{+S+}pring".to_string();
+        assert_eq!(expected, test);
+
+        let test = levenshtein_words("One fine day in spring", "One fine day in Spring")
+            .encoded_edits_nested(encoder, replace_encoder);
+        let expected = "One fine day in {~S~}pring".to_string();
+        assert_eq!(expected, test);
+
+        let test = levenshtein_words("one fine day", "one day")
+            .encoded_edits_nested(encoder, replace_encoder);
+        let expected = "one[- fine-] day".to_string();
+        assert_eq!(expected, test);
+    }
+
+    #[test]
+    fn replace_encoder_checks() {
+        let test = levenshtein("cat", "cup").encoded_edits(replace_encoder);
+        let expected = "c{~up~}".to_string();
+        assert_eq!(expected, test);
+
+        let test = levenshtein("Saturday", "Sunday").encoded_edits(replace_encoder);
+        let expected = "S[-at-]u{~n~}day".to_string();
+        assert_eq!(expected, test);
+    }
+
+    #[test]
+    fn replace_encoder_with_checks() {
+        let encoder = replace_encoder_with(|o, d| format!("{{~{}~}}({})", d, o));
+        let test = levenshtein("cat", "cup").encoded_edits(&encoder);
+        let expected = "c{~up~}(at)".to_string();
+        assert_eq!(expected, test);
+
+        let test = levenshtein("Saturday", "Sunday").encoded_edits(&encoder);
+        let expected = "S[-at-]u{~n~}(r)day".to_string();
+        assert_eq!(expected, test);
+    }
+
     #[test]
     fn t_min_3_always_prefers_lowest_cost() {
         let insertion = Transformation::Insertion(1, "");
@@ -524,20 +1234,46 @@ mod tests {
 
     #[test]
     fn t_delta_checks() {
-        assert_eq!(Transformation::Equality(0, "a"), t_delta(0, "a", "a"));
+        let costs = HashMap::new();
+        assert_eq!(
+            Transformation::Equality(0, "a"),
+            t_delta(0, "a", "a", &costs)
+        );
         assert_eq!(
             Transformation::Substitution(1, "a", "b"),
-            t_delta(0, "a", "b")
+            t_delta(0, "a", "b", &costs)
+        );
+        assert_eq!(
+            Transformation::Equality(0, "a̐"),
+            t_delta(0, "a̐", "a̐", &costs)
         );
-        assert_eq!(Transformation::Equality(0, "a̐"), t_delta(0, "a̐", "a̐"));
         assert_eq!(
             Transformation::Substitution(1, "a̐", "ö̲"),
-            t_delta(0, "a̐", "ö̲")
+            t_delta(0, "a̐", "ö̲", &costs)
+        );
+        assert_eq!(
+            Transformation::Equality(0, "🇸🇹"),
+            t_delta(0, "🇸🇹", "🇸🇹", &costs)
         );
-        assert_eq!(Transformation::Equality(0, "🇸🇹"), t_delta(0, "🇸🇹", "🇸🇹"));
         assert_eq!(
             Transformation::Substitution(1, "🇷🇺", "🇸🇹"),
-            t_delta(0, "🇷🇺", "🇸🇹")
+            t_delta(0, "🇷🇺", "🇸🇹", &costs)
+        );
+
+        let mut costs = HashMap::new();
+        costs.insert("a".to_string(), HashMap::new());
+        costs.get_mut("a").unwrap().insert("b".to_string(), 3);
+        assert_eq!(
+            Transformation::Equality(2, "a"),
+            t_delta(2, "a", "a", &costs)
+        );
+        assert_eq!(
+            Transformation::Substitution(5, "a", "b"),
+            t_delta(2, "a", "b", &costs)
+        );
+        assert_eq!(
+            Transformation::Substitution(3, "b", "a"),
+            t_delta(2, "b", "a", &costs)
         );
     }
 }